@@ -0,0 +1,573 @@
+use regex::Regex;
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+/// Env vars of the form `FZF_ALT_<FILETYPE>_<FIELD>` override a single field
+/// of a single filetype's config. Kept as an explicit list (rather than
+/// splitting on every `_`) because filetype names may themselves contain
+/// underscores: the filetype is whatever precedes the matched field suffix.
+///
+/// This originally also covered the single `is_test`/`strip` regex fields
+/// (e.g. `FZF_ALT_ELIXIR_IS_TEST`), but those were replaced by the `rules`
+/// list's rewrite engine. A `Vec<AlternationRule>` isn't a sensible target
+/// for a single env var, so overriding a rule's regex from the environment
+/// is no longer supported — only `template` is. A `--config` file remains
+/// the way to customize `rules`.
+const OVERRIDE_FIELDS: &[&str] = &["template"];
+
+const ENV_PREFIX: &str = "FZF_ALT_";
+
+/// Errors produced while loading and merging layered configuration.
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, std::io::Error),
+    UnsupportedFormat(PathBuf),
+    Parse(PathBuf, String),
+    /// A field failed to deserialize; `path` is a dotted path such as
+    /// `elixir.rules[0].forward.from` pointing at the offending filetype/field.
+    Deserialize { path: String, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, err) => write!(f, "failed to read {}: {}", path.display(), err),
+            ConfigError::UnsupportedFormat(path) => {
+                write!(f, "unrecognized config format for {}", path.display())
+            }
+            ConfigError::Parse(path, message) => {
+                write!(f, "failed to parse {}: {}", path.display(), message)
+            }
+            ConfigError::Deserialize { path, message } => {
+                write!(f, "invalid config at `{}`: {}", path, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Name of the project-local config file, discovered by walking up from the
+/// current directory (à la Deno's config resolution).
+const PROJECT_CONFIG_FILE: &str = ".fzf_alt.toml";
+
+/// One layer that contributed to a loaded [`AppConfig`], in merge order
+/// (earliest first, later layers win). `--verbose` prints these to explain
+/// where a filetype's rules came from.
+pub struct ConfigLayer {
+    pub kind: &'static str,
+    pub path: Option<PathBuf>,
+}
+
+impl ConfigLayer {
+    fn builtin() -> Self {
+        ConfigLayer {
+            kind: "built-in defaults",
+            path: None,
+        }
+    }
+
+    fn file(kind: &'static str, path: &Path) -> Self {
+        ConfigLayer {
+            kind,
+            path: Some(path.to_owned()),
+        }
+    }
+
+    fn environment() -> Self {
+        ConfigLayer {
+            kind: "environment overrides",
+            path: None,
+        }
+    }
+}
+
+impl fmt::Display for ConfigLayer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{} ({})", self.kind, path.display()),
+            None => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+/// An [`AppConfig`] together with the layers that were merged to produce it.
+pub struct LoadedConfig {
+    pub config: AppConfig,
+    pub layers: Vec<ConfigLayer>,
+}
+
+#[derive(Serialize)]
+pub struct AppConfig(pub HashMap<String, FiletypeConfig>);
+
+/// One direction of a rewrite: `from` is matched against a filename, and
+/// `to` is rendered by substituting each of `from`'s named captures
+/// (`{name}` for a capture named `name`) to produce a candidate path.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Rule {
+    #[serde(with = "serde_regex")]
+    pub from: Regex,
+    pub to: String,
+}
+
+impl Rule {
+    fn candidate(&self, filename: &str) -> Option<String> {
+        let caps = self.from.captures(filename)?;
+        let mut candidate = self.to.clone();
+
+        for name in self.from.capture_names().flatten() {
+            if let Some(value) = caps.name(name) {
+                candidate = candidate.replace(&format!("{{{name}}}"), value.as_str());
+            }
+        }
+
+        Some(candidate)
+    }
+}
+
+/// A bidirectional rewrite rule: `forward` turns a source file into a test
+/// file, `backward` turns that test file back into a source file. Filenames
+/// only ever match one direction, so a single rule set handles both.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AlternationRule {
+    pub forward: Rule,
+    pub backward: Rule,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FiletypeConfig {
+    /// Ordered rewrite rules; earlier rules are preferred when more than
+    /// one produces a candidate that exists.
+    pub rules: Vec<AlternationRule>,
+    /// Content skeleton used to scaffold a missing alternate file. `{name}`
+    /// is substituted with the name captured by whichever rule matched,
+    /// camelized and dot-joined per path segment (`example/content` becomes
+    /// `Example.Content`).
+    pub template: String,
+    /// File extensions (without the leading dot) that auto-detect this
+    /// filetype when none is given explicitly, e.g. `["ex", "exs"]`.
+    pub extensions: Vec<String>,
+}
+
+impl FiletypeConfig {
+    /// Whether `filename` looks like a test file, i.e. some rule's
+    /// `backward` direction (test -> source) matches it.
+    pub fn is_test(&self, filename: &str) -> bool {
+        self.rules.iter().any(|rule| rule.backward.from.is_match(filename))
+    }
+
+    /// The ordered set of candidate alternate paths for `filename`,
+    /// produced by applying every rule that matches in either direction.
+    pub fn candidates(&self, filename: &str) -> Vec<String> {
+        self.rules
+            .iter()
+            .filter_map(|rule| {
+                rule.forward
+                    .candidate(filename)
+                    .or_else(|| rule.backward.candidate(filename))
+            })
+            .collect()
+    }
+
+    /// The name captured by whichever rule matched `filename`, used to
+    /// render `template`. Falls back to `filename` itself when no rule
+    /// matches.
+    pub fn captured_name(&self, filename: &str) -> String {
+        self.rules
+            .iter()
+            .find_map(|rule| {
+                rule.forward
+                    .from
+                    .captures(filename)
+                    .or_else(|| rule.backward.from.captures(filename))
+                    .and_then(|caps| caps.name("name").map(|m| m.as_str().to_owned()))
+            })
+            .unwrap_or_else(|| filename.to_owned())
+    }
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        let mut config_map = HashMap::new();
+
+        let elixir_config = FiletypeConfig {
+            rules: vec![AlternationRule {
+                forward: Rule {
+                    from: Regex::new(r"^(?P<dir>.*)lib/(?P<name>.+)\.exs?$").unwrap(),
+                    to: "{dir}test/{name}_test.exs".to_owned(),
+                },
+                backward: Rule {
+                    from: Regex::new(r"^(?P<dir>.*)test/(?P<name>.+)_test\.exs$").unwrap(),
+                    to: "{dir}lib/{name}.ex".to_owned(),
+                },
+            }],
+            template: "defmodule {name}Test do\n  use ExUnit.Case\nend\n".to_owned(),
+            extensions: vec!["ex".to_owned(), "exs".to_owned()],
+        };
+
+        config_map.insert("elixir".to_owned(), elixir_config);
+
+        let python_config = FiletypeConfig {
+            rules: vec![AlternationRule {
+                forward: Rule {
+                    from: Regex::new(r"^(?P<dir>.*)src/(?P<name>\w+)\.py$").unwrap(),
+                    to: "{dir}tests/test_{name}.py".to_owned(),
+                },
+                backward: Rule {
+                    from: Regex::new(r"^(?P<dir>.*)tests?/test_(?P<name>\w+)\.py$").unwrap(),
+                    to: "{dir}src/{name}.py".to_owned(),
+                },
+            }],
+            template: "import unittest\n\n\nclass Test{name}(unittest.TestCase):\n    pass\n".to_owned(),
+            extensions: vec!["py".to_owned()],
+        };
+
+        config_map.insert("python".to_owned(), python_config);
+
+        AppConfig(config_map)
+    }
+}
+
+// A Visitor is a type that holds methods that a Deserializer can drive
+// depending on what is contained in the input data.
+//
+// This is an example of a "zero sized type" in Rust. The PhantomData
+// keeps the compiler from complaining about unused generic type
+// parameters.
+struct AppConfigVisitor {
+    marker: PhantomData<fn() -> AppConfig>,
+}
+
+impl AppConfigVisitor {
+    fn new() -> Self {
+        Self {
+            marker: PhantomData,
+        }
+    }
+}
+
+// This is the trait that Deserializers are going to be driving. There
+// is one method for each type of data that our type knows how to
+// deserialize from. There are many other methods that are not
+// implemented here, for example deserializing from integers or strings.
+// By default those methods will return an error, which makes sense
+// because we cannot deserialize a MyMap from an integer or string.
+impl<'de> Visitor<'de> for AppConfigVisitor {
+    // The type that our Visitor is going to produce.
+    type Value = AppConfig;
+
+    // Format a message stating what data this Visitor expects to receive.
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("fzf_alt app config")
+    }
+
+    // Deserialize AppConfig from an abstract "map" provided by the
+    // Deserializer. The MapAccess input is a callback provided by
+    // the Deserializer to let us see each entry in the map.
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut map = HashMap::with_capacity(access.size_hint().unwrap_or(0));
+
+        // While there are entries remaining in the input, add them
+        // into our map.
+        while let Some((key, value)) = access.next_entry()? {
+            map.insert(key, value);
+        }
+
+        Ok(AppConfig(map))
+    }
+}
+
+impl<'de> Deserialize<'de> for AppConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(AppConfigVisitor::new())
+    }
+}
+
+impl AppConfig {
+    pub fn get_filetype_config(&self, filetype: &str) -> Option<&FiletypeConfig> {
+        self.0.get(filetype)
+    }
+
+    /// Auto-detects a filetype from `filename`'s extension by looking for
+    /// the one filetype whose `extensions` list claims it. Returns `None`
+    /// when the extension is unknown or claimed by more than one filetype,
+    /// so callers can fall back to requiring an explicit filetype argument.
+    pub fn resolve_filetype(&self, filename: &str) -> Option<&str> {
+        let ext = Path::new(filename).extension()?.to_str()?;
+
+        let mut matches = self
+            .0
+            .iter()
+            .filter(|(_, cfg)| cfg.extensions.iter().any(|candidate| candidate == ext));
+
+        let (filetype, _) = matches.next()?;
+        if matches.next().is_some() {
+            return None;
+        }
+
+        Some(filetype.as_str())
+    }
+
+    /// Loads the layered configuration: built-in defaults, then the user's
+    /// global config file (if any), then a project-local `.fzf_alt.toml`
+    /// discovered by walking up from the current directory (if any), then
+    /// an explicit `--config` file (if given), then `FZF_ALT_*` environment
+    /// overrides. Each layer is deep-merged on top of the previous one field
+    /// by field, so e.g. a project file can redefine `python.template`
+    /// without touching `python.rules` or any other filetype.
+    pub fn load(explicit_path: Option<&Path>) -> Result<LoadedConfig, ConfigError> {
+        let mut merged =
+            serde_json::to_value(Self::default()).expect("AppConfig::default() is always serializable");
+        let mut layers = vec![ConfigLayer::builtin()];
+
+        if let Some(global_path) = global_config_path() {
+            deep_merge(&mut merged, load_value_from_file(&global_path)?);
+            layers.push(ConfigLayer::file("global", &global_path));
+        }
+
+        if let Some(project_path) = discover_project_config() {
+            deep_merge(&mut merged, load_value_from_file(&project_path)?);
+            layers.push(ConfigLayer::file("project", &project_path));
+        }
+
+        if let Some(explicit_path) = explicit_path {
+            deep_merge(&mut merged, load_value_from_file(explicit_path)?);
+            layers.push(ConfigLayer::file("explicit", explicit_path));
+        }
+
+        let env_overrides = env_overrides(ENV_PREFIX);
+        if !env_overrides.as_object().is_some_and(|map| map.is_empty()) {
+            deep_merge(&mut merged, env_overrides);
+            layers.push(ConfigLayer::environment());
+        }
+
+        let config = serde_path_to_error::deserialize(merged).map_err(|err| ConfigError::Deserialize {
+            path: err.path().to_string(),
+            message: err.to_string(),
+        })?;
+
+        Ok(LoadedConfig { config, layers })
+    }
+}
+
+/// Walks up from the current directory looking for a project-local
+/// `.fzf_alt.toml`, the way Deno resolves `deno.json`. The search starts at
+/// the (canonicalized) working directory and stops as soon as a match is
+/// found, or at the filesystem root.
+fn discover_project_config() -> Option<PathBuf> {
+    let cwd = env::current_dir().ok()?.canonicalize().ok()?;
+
+    discover_project_config_from(&cwd)
+}
+
+fn discover_project_config_from(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+/// `~/.config/fzf_alt/config.{toml,yaml,yml,json}`, whichever exists first.
+fn global_config_path() -> Option<PathBuf> {
+    let dir = dirs::config_dir()?.join("fzf_alt");
+
+    ["toml", "yaml", "yml", "json"]
+        .iter()
+        .map(|ext| dir.join("config").with_extension(ext))
+        .find(|path| path.is_file())
+}
+
+fn load_value_from_file(path: &Path) -> Result<JsonValue, ConfigError> {
+    let contents = fs::read_to_string(path).map_err(|err| ConfigError::Io(path.to_owned(), err))?;
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => {
+            toml::from_str(&contents).map_err(|err| ConfigError::Parse(path.to_owned(), err.to_string()))
+        }
+        Some("yaml") | Some("yml") => serde_yaml::from_str(&contents)
+            .map_err(|err| ConfigError::Parse(path.to_owned(), err.to_string())),
+        Some("json") => serde_json::from_str(&contents)
+            .map_err(|err| ConfigError::Parse(path.to_owned(), err.to_string())),
+        _ => Err(ConfigError::UnsupportedFormat(path.to_owned())),
+    }
+}
+
+/// Deep-merges `overlay` into `base`: objects are merged key by key,
+/// everything else is replaced outright.
+fn deep_merge(base: &mut JsonValue, overlay: JsonValue) {
+    match (base, overlay) {
+        (JsonValue::Object(base_map), JsonValue::Object(overlay_map)) => {
+            for (key, value) in overlay_map {
+                deep_merge(base_map.entry(key).or_insert(JsonValue::Null), value);
+            }
+        }
+        (base_slot, overlay_value) => *base_slot = overlay_value,
+    }
+}
+
+/// Builds a `{filetype: {field: value}}` value tree out of `FZF_ALT_*`
+/// environment variables, e.g. `FZF_ALT_ELIXIR_TEMPLATE` overrides
+/// `elixir.template`.
+fn env_overrides(prefix: &str) -> JsonValue {
+    let mut overrides = serde_json::Map::new();
+
+    for (key, value) in env::vars() {
+        let Some(rest) = key.strip_prefix(prefix) else {
+            continue;
+        };
+        let rest = rest.to_lowercase();
+
+        let Some(field) = OVERRIDE_FIELDS
+            .iter()
+            .find(|field| rest.ends_with(&format!("_{field}")))
+        else {
+            continue;
+        };
+
+        let filetype = &rest[..rest.len() - field.len() - 1];
+        if filetype.is_empty() {
+            continue;
+        }
+
+        overrides
+            .entry(filetype.to_owned())
+            .or_insert_with(|| JsonValue::Object(serde_json::Map::new()))
+            .as_object_mut()
+            .unwrap()
+            .insert((*field).to_owned(), JsonValue::String(value));
+    }
+
+    JsonValue::Object(overrides)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_deep_merge_overlays_win_and_untouched_keys_survive() {
+        let mut base = json!({
+            "elixir": {"template": "old", "extensions": ["ex", "exs"]},
+            "python": {"template": "unchanged"},
+        });
+
+        deep_merge(
+            &mut base,
+            json!({"elixir": {"template": "new"}}),
+        );
+
+        assert_eq!(base["elixir"]["template"], "new");
+        assert_eq!(base["elixir"]["extensions"], json!(["ex", "exs"]));
+        assert_eq!(base["python"]["template"], "unchanged");
+    }
+
+    #[test]
+    fn test_deep_merge_replaces_non_object_values_outright() {
+        let mut base = json!({"rules": ["a", "b"]});
+
+        deep_merge(&mut base, json!({"rules": ["c"]}));
+
+        assert_eq!(base["rules"], json!(["c"]));
+    }
+
+    #[test]
+    fn test_env_overrides_reads_matching_prefixed_vars_only() {
+        let prefix = "FZF_ALT_TEST_ENV_OVERRIDES_";
+        env::set_var(format!("{prefix}ELIXIR_TEMPLATE"), "from env");
+        env::set_var(format!("{prefix}ELIXIR_UNKNOWN_FIELD"), "ignored");
+
+        let overrides = env_overrides(prefix);
+
+        env::remove_var(format!("{prefix}ELIXIR_TEMPLATE"));
+        env::remove_var(format!("{prefix}ELIXIR_UNKNOWN_FIELD"));
+
+        assert_eq!(overrides["elixir"]["template"], "from env");
+        assert_eq!(overrides["elixir"].get("unknown_field"), None);
+    }
+
+    #[test]
+    fn test_load_value_from_file_autodetects_format_by_extension() {
+        for (suffix, contents, expected) in [
+            ("toml", "template = \"t\"\n", json!({"template": "t"})),
+            ("yaml", "template: t\n", json!({"template": "t"})),
+            ("json", "{\"template\": \"t\"}", json!({"template": "t"})),
+        ] {
+            let mut file = tempfile::Builder::new()
+                .suffix(&format!(".{suffix}"))
+                .tempfile()
+                .expect("failed to create temp config file");
+            use std::io::Write;
+            write!(file, "{contents}").expect("failed to write temp config file");
+
+            let value = load_value_from_file(file.path()).expect("failed to load config file");
+            assert_eq!(value, expected, "mismatch for .{suffix}");
+        }
+    }
+
+    #[test]
+    fn test_load_value_from_file_rejects_unknown_extension() {
+        let file = tempfile::Builder::new()
+            .suffix(".ini")
+            .tempfile()
+            .expect("failed to create temp config file");
+
+        let err = load_value_from_file(file.path()).unwrap_err();
+        assert!(matches!(err, ConfigError::UnsupportedFormat(_)));
+    }
+
+    #[test]
+    fn test_discover_project_config_walks_up_to_nearest_match() {
+        let root = tempfile::tempdir().expect("failed to create temp dir");
+        let nested = root.path().join("a/b/c");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.path().join("a").join(PROJECT_CONFIG_FILE), "").unwrap();
+
+        let found = discover_project_config_from(&nested).expect("expected to find a project config");
+
+        assert_eq!(found, root.path().join("a").join(PROJECT_CONFIG_FILE));
+    }
+
+    #[test]
+    fn test_discover_project_config_none_when_absent() {
+        let root = tempfile::tempdir().expect("failed to create temp dir");
+        let nested = root.path().join("a/b/c");
+        fs::create_dir_all(&nested).unwrap();
+
+        assert_eq!(discover_project_config_from(&nested), None);
+    }
+
+    #[test]
+    fn test_deserialize_error_surfaces_offending_field_path() {
+        let value = json!({
+            "elixir": {
+                "rules": [{
+                    "forward": {"from": "(", "to": "x"},
+                    "backward": {"from": "^$", "to": "y"},
+                }],
+                "template": "t",
+                "extensions": [],
+            },
+        });
+
+        let result: Result<AppConfig, _> = serde_path_to_error::deserialize(value);
+        let Err(err) = result else {
+            panic!("expected deserialization to fail on an invalid regex");
+        };
+
+        assert_eq!(err.path().to_string(), "elixir.rules[0].forward.from");
+    }
+}
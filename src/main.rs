@@ -1,51 +1,160 @@
-use confy;
-use fzf_alt::config::AppConfig;
-use regex::Regex;
-use std::env::args;
+use clap::Parser;
+use fzf_alt::config::{AppConfig, FiletypeConfig};
 use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::{exit, Command, Stdio};
+use tracing::{error, info, trace, warn};
+use tracing_subscriber::filter::LevelFilter;
+
+/// Jump to (or scaffold) the alternate file for `filename` - e.g. a module
+/// and its test.
+#[derive(Parser)]
+#[command(name = "fzf_alt", version, about)]
+struct Opts {
+    /// File to find (or scaffold) the alternate for.
+    filename: String,
+
+    /// Filetype to use from the config, e.g. `elixir` or `python`. Guessed
+    /// from `filename`'s extension when omitted, as long as exactly one
+    /// configured filetype claims that extension.
+    filetype: Option<String>,
+
+    /// Path to scaffold the alternate at, if it doesn't already exist. Named
+    /// (rather than a second positional) so it can be combined with a
+    /// guessed `filetype`, e.g. `fzf_alt lib/foo.ex --create test/foo_test.exs`.
+    #[arg(long)]
+    create: Option<String>,
+
+    /// Explicit config file, layered on top of the global and project
+    /// configs and below environment overrides.
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// Increase verbosity (-v info, -vv debug, -vvv trace).
+    #[arg(short = 'v', long = "verbose", action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Decrease verbosity (-q errors only, -qq silent).
+    #[arg(short = 'q', long = "quiet", action = clap::ArgAction::Count, conflicts_with = "verbose")]
+    quiet: u8,
+}
+
+impl Opts {
+    fn tracing_level(&self) -> LevelFilter {
+        match self.verbose as i8 - self.quiet as i8 {
+            ..=-2 => LevelFilter::OFF,
+            -1 => LevelFilter::ERROR,
+            0 => LevelFilter::WARN,
+            1 => LevelFilter::INFO,
+            2 => LevelFilter::DEBUG,
+            3.. => LevelFilter::TRACE,
+        }
+    }
+}
 
 struct Alternate {
     filename: String,
-    is_test_regex: Regex,
-    strip_regex: Regex,
+    cfg: FiletypeConfig,
 }
 
 impl Alternate {
-    fn new(filetype: String, filename: String) -> Option<Alternate> {
-        let cfg: AppConfig = confy::load("fzf_alt").expect("Failed to load fzf_alt config");
+    fn new(filename: String, cfg: FiletypeConfig) -> Alternate {
+        Alternate { filename, cfg }
+    }
 
-        let filetype_cfg = cfg.get_filetype_config(&filetype)?;
+    /// Fuzzy-search anchor handed to `fzf`: the rule engine needs the real
+    /// file list to pick a candidate, so this only has to narrow it down.
+    /// It is the last segment of the name the matching rule captures (e.g.
+    /// `content` for both `lib/example/content.ex` and
+    /// `test/example/content/content_test.exs`), so both a file and its
+    /// alternate survive the filter; without a matching rule it is the
+    /// file stem.
+    fn search_term(&self) -> String {
+        let name = self.cfg.captured_name(&self.filename);
+        Path::new(&name)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or(&name)
+            .to_owned()
+    }
 
-        Some(Alternate {
-            strip_regex: filetype_cfg.strip.to_owned(),
-            is_test_regex: filetype_cfg.is_test.to_owned(),
-            filename: filename,
-        })
+    fn is_test(&self) -> bool {
+        self.cfg.is_test(&self.filename)
     }
 
-    fn strip_filename(&self) -> &str {
-        self.strip_regex
-            .captures(&self.filename)
-            .and_then(|caps| caps.name("p"))
-            .map(|m| m.as_str())
-            .unwrap_or(&self.filename)
+    /// Picks the alternate file out of `files` (fzf's fuzzy-matched
+    /// output): a rule-engine candidate that actually exists in `files` is
+    /// preferred, in rule order; otherwise falls back to the first file of
+    /// opposite test-ness, same as before the rule engine existed.
+    fn get_alternate_file<'a>(&self, files: &'a str) -> Option<&'a str> {
+        let files: Vec<&str> = files.split_whitespace().collect();
+        let candidates = self.cfg.candidates(&self.filename);
+
+        let matched = candidates.iter().enumerate().find_map(|(index, candidate)| {
+            files
+                .iter()
+                .find(|file| *file == candidate)
+                .map(|file| (index, *file))
+        });
+
+        if let Some((index, file)) = matched {
+            // `candidates` skips rules that match in neither direction, so
+            // map the candidate's position back to the rule that produced it.
+            let rule = self
+                .cfg
+                .rules
+                .iter()
+                .enumerate()
+                .filter(|(_, rule)| {
+                    rule.forward.from.is_match(&self.filename)
+                        || rule.backward.from.is_match(&self.filename)
+                })
+                .nth(index)
+                .map_or(index, |(rule, _)| rule);
+            trace!(rule, file, "alternation rule produced an exact match");
+            return Some(file);
+        }
+
+        trace!("no rule candidate found among fzf results, falling back to opposite test-ness");
+        files
+            .into_iter()
+            .find(|file| self.cfg.is_test(file) ^ self.is_test())
     }
 
-    fn is_test(&self, filename: &str) -> bool {
-        self.is_test_regex.is_match(filename)
+    /// Renders this filetype's content skeleton for a file that does not
+    /// exist yet. The captured name is camelized (and, for a nested path
+    /// like `example/content`, dot-joined per segment) so `{name}` always
+    /// substitutes to a valid module identifier, e.g. `Example.Content`.
+    fn scaffold_content(&self) -> String {
+        let name = camelize(&self.cfg.captured_name(&self.filename));
+        self.cfg.template.replace("{name}", &name)
     }
+}
 
-    fn get_alternate_file<'a>(&'a self, files: &'a str) -> Option<&'a str> {
-        let mut result = files
-            .split_whitespace()
-            .filter(|file| self.is_test(file) ^ self.is_test(&self.filename));
+/// Turns a captured path fragment such as `example/content` or
+/// `module_question` into a dotted CamelCase identifier, e.g.
+/// `Example.Content` or `ModuleQuestion`. Each `/`-separated segment
+/// becomes one dot-joined part, and each part has its `_`-separated words
+/// capitalized and joined.
+fn camelize(name: &str) -> String {
+    name.split('/')
+        .map(|segment| segment.split('_').map(capitalize).collect::<String>())
+        .collect::<Vec<_>>()
+        .join(".")
+}
 
-        result.next()
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+        None => String::new(),
     }
 }
 
 fn run_fzf(input: &str, stdin: impl Into<Stdio>) -> String {
+    trace!(pattern = input, "running fzf");
+
     let child = Command::new("fzf")
         .args(&["-f", input, "--no-sort", "--inline-info"])
         .stdout(Stdio::piped())
@@ -56,46 +165,77 @@ fn run_fzf(input: &str, stdin: impl Into<Stdio>) -> String {
     let output = child
         .wait_with_output()
         .expect("Failed to wait fzf command");
-    String::from_utf8_lossy(&output.stdout).to_string()
+    let output = String::from_utf8_lossy(&output.stdout).to_string();
+
+    trace!(output, "fzf returned");
+
+    output
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = args().collect();
+    let opts = Opts::parse();
+
+    tracing_subscriber::fmt()
+        .with_max_level(opts.tracing_level())
+        .without_time()
+        .with_target(false)
+        .init();
 
-    if args.len() < 2 {
-        eprintln!("too few args provided");
+    let loaded = AppConfig::load(opts.config.as_deref()).unwrap_or_else(|err| {
+        error!("{}", err);
         exit(1);
+    });
+
+    for (index, layer) in loaded.layers.iter().enumerate() {
+        info!(index, %layer, "config layer merged");
     }
 
-    // Guaranteed to exist, because we check previously if args is empty
-    let filename = if let Some(filename) = args.get(1) {
-        filename
-    } else {
-        eprintln!("filename should be provided");
-        exit(1);
-    };
+    let filetype = opts
+        .filetype
+        .clone()
+        .or_else(|| loaded.config.resolve_filetype(&opts.filename).map(str::to_owned))
+        .unwrap_or_else(|| {
+            error!("filetype should be provided");
+            exit(1);
+        });
 
-    match (args.get(2), args.get(3)) {
-        (None, None) => {
-            eprintln!("filetype should be provided");
+    let cfg = loaded
+        .config
+        .get_filetype_config(&filetype)
+        .unwrap_or_else(|| {
+            error!("{} not found in fzf_alt config", filetype);
             exit(1);
+        })
+        .clone();
+
+    let alternate = Alternate::new(opts.filename.clone(), cfg);
+
+    match &opts.create {
+        None => {
+            let files = run_fzf(&alternate.search_term(), Stdio::inherit());
+
+            match alternate.get_alternate_file(&files) {
+                Some(result) => println!("{}", result),
+                None => {
+                    warn!("no alternate file found for {}", opts.filename);
+                    exit(1);
+                }
+            }
         }
-        (Some(filetype), None) => {
-            let alternate = Alternate::new(filetype.to_owned(), filename.to_owned());
-            let alternate = if let Some(alt) = alternate {
-                alt
-            } else {
-                eprintln!("{} not found in fzf_alt config", filetype);
-                exit(1)
-            };
+        Some(alternate_path) => {
+            let target = Path::new(alternate_path);
 
-            let files = run_fzf(alternate.strip_filename(), Stdio::inherit());
-            let result = alternate.get_alternate_file(&files);
+            if target.exists() {
+                println!("{} already exists", target.display());
+            } else {
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
 
-            println!("{}", result.unwrap_or_else(|| exit(1)));
+                fs::write(target, alternate.scaffold_content())?;
+                println!("created {}", target.display());
+            }
         }
-        (Some(_filetype), Some(_alternate)) => {}
-        _ => unreachable!(),
     }
 
     Ok(())
@@ -105,6 +245,8 @@ fn main() -> Result<(), Box<dyn Error>> {
 mod test {
     use super::*;
 
+    use fzf_alt::config::{AlternationRule, Rule};
+    use regex::Regex;
     use std::io::prelude::*;
     use std::io::SeekFrom;
     use tempfile::tempfile;
@@ -182,12 +324,18 @@ lib/example_web/templates/page/index.html.eex
         run_fzf(input, tmp_file)
     }
 
+    fn elixir_config() -> FiletypeConfig {
+        AppConfig::default()
+            .get_filetype_config("elixir")
+            .expect("elixir not found in fzf_alt config")
+            .clone()
+    }
+
     #[test]
     fn test_elixir_content_alternate() {
-        let alternate = Alternate::new("elixir".to_owned(), "lib/example/content.ex".to_owned())
-            .expect("elixir not found in fzf_alt config");
+        let alternate = Alternate::new("lib/example/content.ex".to_owned(), elixir_config());
 
-        let test_case = test_case_fixture(alternate.strip_filename());
+        let test_case = test_case_fixture(&alternate.search_term());
 
         assert_eq!(
             alternate.get_alternate_file(&test_case),
@@ -198,16 +346,78 @@ lib/example_web/templates/page/index.html.eex
     #[test]
     fn test_elixir_content_test_alternate() {
         let alternate = Alternate::new(
-            "elixir".to_owned(),
             "test/example/content/content_test.exs".to_owned(),
-        )
-        .expect("elixir not found in fzf_alt config");
+            elixir_config(),
+        );
 
-        let test_case = test_case_fixture(alternate.strip_filename());
+        let test_case = test_case_fixture(&alternate.search_term());
 
         assert_eq!(
             alternate.get_alternate_file(&test_case),
             Some("lib/example/content.ex")
         );
     }
+
+    #[test]
+    fn test_scaffold_content_is_valid_elixir_module() {
+        let alternate = Alternate::new("lib/example/content.ex".to_owned(), elixir_config());
+
+        let content = alternate.scaffold_content();
+
+        assert_eq!(
+            content,
+            "defmodule Example.ContentTest do\n  use ExUnit.Case\nend\n"
+        );
+        assert!(!content.contains('/'), "module name must not contain a path separator");
+    }
+
+    /// A JS-style filetype whose rules match the layout of the file list in
+    /// the test below (flat `src/`/`__tests__/` directories), so its
+    /// candidates land on an exact match instead of falling back to the
+    /// opposite-test-ness guess. The Elixir fixture above uses a nested
+    /// layout (`test/example/content/content_test.exs`) that the default
+    /// Elixir rule does not produce, so those tests go through the fallback.
+    fn javascript_config() -> FiletypeConfig {
+        FiletypeConfig {
+            rules: vec![AlternationRule {
+                forward: Rule {
+                    from: Regex::new(r"^(?P<dir>.*)src/(?P<name>.+)\.js$").unwrap(),
+                    to: "{dir}__tests__/{name}.test.js".to_owned(),
+                },
+                backward: Rule {
+                    from: Regex::new(r"^(?P<dir>.*)__tests__/(?P<name>.+)\.test\.js$").unwrap(),
+                    to: "{dir}src/{name}.js".to_owned(),
+                },
+            }],
+            template: "test('{name}', () => {});\n".to_owned(),
+            extensions: vec!["js".to_owned()],
+        }
+    }
+
+    #[test]
+    fn test_candidates_prefer_exact_rule_match_over_fallback() {
+        let alternate = Alternate::new("src/foo.js".to_owned(), javascript_config());
+
+        // The opposite-test-ness fallback would pick `bar.test.js`, since it
+        // comes first; the rule engine should instead prefer the exact
+        // structural match `foo.test.js`.
+        let files = "__tests__/bar.test.js __tests__/foo.test.js";
+
+        assert_eq!(
+            alternate.get_alternate_file(files),
+            Some("__tests__/foo.test.js")
+        );
+    }
+
+    #[test]
+    fn test_resolve_filetype_from_extension() {
+        let config = AppConfig::default();
+
+        assert_eq!(
+            config.resolve_filetype("lib/example/content.ex"),
+            Some("elixir")
+        );
+        assert_eq!(config.resolve_filetype("src/example.py"), Some("python"));
+        assert_eq!(config.resolve_filetype("README.md"), None);
+    }
 }